@@ -13,9 +13,13 @@
 //! lookup tables of pre-computed points.
 
 use field_64bit::FieldElement64;
-use edwards::ExtendedPoint;
+use edwards::{EdwardsBasepointTable, ExtendedPoint};
 
 /// Edwards `d` value, equal to `-121665/121666 mod p`.
+///
+/// Kept alongside `EDWARDS_D2` for reference even though the point
+/// formulas in `edwards` only need the doubled form.
+#[allow(dead_code)]
 pub(crate) const EDWARDS_D: FieldElement64 = FieldElement64([929955233495203, 466365720129213, 1662059464998953, 2033849074728123, 1442794654840575]);
 
 /// Edwards `2*d` value, equal to `2*(-121665/121666) mod p`.
@@ -34,11 +38,38 @@ pub(crate) const INVSQRT_A_MINUS_D: FieldElement64 = FieldElement64([
 /// Precomputed value of one of the square roots of -1 (mod p)
 pub(crate) const SQRT_M1: FieldElement64 = FieldElement64([1718705420411056, 234908883556509, 2233514472574048, 2117202627021982, 765476049583133]);
 
+/// The order of the prime-order subgroup generated by the Ed25519 basepoint,
+/// `\ell = 2^252 + 27742317777372353535851937790883648493`, packed as five
+/// 52-bit limbs for use in `Scalar` Montgomery arithmetic.
+pub(crate) const L: [u64; 5] = [
+    0x0002631a5cf5d3ed,
+    0x000dea2f79cd6581,
+    0x000000000014def9,
+    0x0000000000000000,
+    0x0000100000000000,
+];
+
+/// `-(L^-1) mod 2^52`, used as the Montgomery reduction factor for `L`.
+pub(crate) const LFACTOR: u64 = 0x51da312547e1b;
+
+/// `R^2 mod L`, where `R = 2^260`, used to convert scalars into and out
+/// of Montgomery form.
+pub(crate) const RR: [u64; 5] = [
+    0x0009d265e952d13b,
+    0x000d63c715bea69f,
+    0x0005be65cb687604,
+    0x0003dceec73d217f,
+    0x000009411b7c309a,
+];
+
 /// In Montgomery form y² = x³+Ax²+x, Curve25519 has A=486662.
 pub(crate) const MONTGOMERY_A: FieldElement64 = FieldElement64([486662, 0, 0, 0, 0]);
 
-/// `APLUS2_OVER_FOUR` is (A+2)/4. (This is used internally within the Montgomery ladder.)
-pub(crate) const APLUS2_OVER_FOUR: FieldElement64 = FieldElement64([121666, 0, 0, 0, 0]);
+/// `MONTGOMERY_A24` is `(A-2)/4 = 121665`, the `a24` constant from the
+/// single ladder step in RFC 7748 §5. (This is used internally within
+/// the Montgomery ladder; it is unrelated to `SQRT_MINUS_APLUS2` below,
+/// which really does need `A+2`.)
+pub(crate) const MONTGOMERY_A24: FieldElement64 = FieldElement64([121665, 0, 0, 0, 0]);
 
 /// `SQRT_MINUS_APLUS2` is sqrt(-486664)
 pub(crate) const SQRT_MINUS_APLUS2: FieldElement64 = FieldElement64([1693982333959686, 608509411481997, 2235573344831311, 947681270984193, 266558006233600]);
@@ -53,6 +84,18 @@ pub const ED25519_BASEPOINT_POINT: ExtendedPoint = ExtendedPoint{
     T: FieldElement64([1841354044333475, 16398895984059, 755974180946558, 900171276175154, 1821297809914039]),
 };
 
+/// Build the basepoint lookup table used for fast scalar multiplication
+/// by the Ed25519 basepoint.
+///
+/// This is a `fn` rather than a precomputed `const`/`static`, because
+/// the windowed table below is built out of non-`const` field
+/// arithmetic; regenerating it from `ED25519_BASEPOINT_POINT` keeps a
+/// large literal array out of the source and lets it be checked against
+/// the basepoint directly, rather than trusting a hardcoded blob.
+pub fn ed25519_basepoint_table() -> EdwardsBasepointTable {
+    EdwardsBasepointTable::create(&ED25519_BASEPOINT_POINT)
+}
+
 /// The 8-torsion subgroup Ɛ[8].
 ///
 /// In the case of Curve25519, it is cyclic; the `i`th element of the
@@ -117,3 +160,26 @@ pub const EIGHT_TORSION: [ExtendedPoint; 8] = [
         T: FieldElement64([803472979097708, 393902981724766, 1158077081819914, 574391322974006, 336294660666841]),
     }
 ];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use scalar::Scalar;
+
+    #[test]
+    fn basepoint_table_matches_basepoint_point() {
+        let mut bytes = [0u8; 32];
+        bytes[0] = 1;
+        let one = Scalar::from_bytes_mod_order(bytes);
+        assert_eq!(&ed25519_basepoint_table() * &one, ED25519_BASEPOINT_POINT);
+    }
+
+    #[test]
+    fn basepoint_table_agrees_with_doubling_and_adding() {
+        let mut bytes = [0u8; 32];
+        bytes[0] = 3;
+        let three = Scalar::from_bytes_mod_order(bytes);
+        let expected = ED25519_BASEPOINT_POINT.double().add(&ED25519_BASEPOINT_POINT);
+        assert_eq!(&ed25519_basepoint_table() * &three, expected);
+    }
+}
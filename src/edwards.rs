@@ -0,0 +1,408 @@
+// -*- mode: rust; -*-
+//
+// This file is part of curve25519-dalek.
+// Copyright (c) 2016-2017 Isis Lovecruft, Henry de Valence
+// See LICENSE for licensing information.
+//
+// Authors:
+// - Isis Agora Lovecruft <isis@patternsinthevoid.net>
+// - Henry de Valence <hdevalence@hdevalence.ca>
+
+//! Group operations for the twisted Edwards curve
+//!
+//! \\( -x\^2 + y\^2 = 1 + d x\^2 y\^2 \\)
+//!
+//! used by Ed25519, in *extended* coordinates \\( (X:Y:Z:T) \\) with
+//! \\( x = X/Z, y = Y/Z, xy = T/Z \\).
+
+use std::ops::{Add, Mul, Neg};
+
+use field_64bit::FieldElement64;
+use constants_64bit::EDWARDS_D2;
+use montgomery::MontgomeryPoint;
+use scalar::Scalar;
+
+/// The order of the large prime-order subgroup generated by the Ed25519
+/// basepoint, `\ell = 2^252 + 27742317777372353535851937790883648493`, as
+/// a little-endian byte array.
+const GROUP_ORDER: [u8; 32] = [
+    0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10,
+];
+
+/// An `ExtendedPoint` is a point on Curve25519, represented internally
+/// in extended twisted Edwards coordinates \\( (X:Y:Z:T) \\).
+#[derive(Copy, Clone, Debug)]
+#[allow(non_snake_case)]
+pub struct ExtendedPoint {
+    pub X: FieldElement64,
+    pub Y: FieldElement64,
+    pub Z: FieldElement64,
+    pub T: FieldElement64,
+}
+
+impl ExtendedPoint {
+    /// Return the identity element \\( (0:1:1:0) \\).
+    pub fn identity() -> ExtendedPoint {
+        ExtendedPoint {
+            X: FieldElement64::zero(),
+            Y: FieldElement64::one(),
+            Z: FieldElement64::one(),
+            T: FieldElement64::zero(),
+        }
+    }
+
+    /// Add two extended points using the unified addition formulas for
+    /// twisted Edwards curves with \\( a = -1 \\) (Hisil-Wong-Carter-Dawson).
+    #[allow(non_snake_case)]
+    pub fn add(&self, other: &ExtendedPoint) -> ExtendedPoint {
+        let A = &(&self.Y - &self.X) * &(&other.Y - &other.X);
+        let B = &(&self.Y + &self.X) * &(&other.Y + &other.X);
+        let C = &(&self.T * &EDWARDS_D2) * &other.T;
+        let D = &(&self.Z + &self.Z) * &other.Z;
+        let E = &B - &A;
+        let F = &D - &C;
+        let G = &D + &C;
+        let H = &B + &A;
+
+        ExtendedPoint {
+            X: &E * &F,
+            Y: &G * &H,
+            Z: &F * &G,
+            T: &E * &H,
+        }
+    }
+
+    /// Double this point.
+    #[allow(non_snake_case)]
+    pub fn double(&self) -> ExtendedPoint {
+        let XX = self.X.square();
+        let YY = self.Y.square();
+        let ZZ2 = self.Z.square2();
+        let xplusy = &self.X + &self.Y;
+        let xplusy_sq = xplusy.square();
+        let YY_plus_XX = &YY + &XX;
+        let YY_minus_XX = &YY - &XX;
+        let e = &xplusy_sq - &YY_plus_XX;
+        let f = &YY_minus_XX - &ZZ2;
+        let h = -&YY_plus_XX;
+
+        ExtendedPoint {
+            X: &e * &f,
+            Y: &YY_minus_XX * &h,
+            Z: &f * &YY_minus_XX,
+            T: &e * &h,
+        }
+    }
+
+    /// Negate this point.
+    pub fn negate(&self) -> ExtendedPoint {
+        ExtendedPoint {
+            X: -&self.X,
+            Y: self.Y,
+            Z: self.Z,
+            T: -&self.T,
+        }
+    }
+
+    /// Compute a variable-time scalar multiplication `[bytes] * self`,
+    /// where `bytes` is the little-endian encoding of the scalar. This
+    /// is a simple double-and-add ladder; it is only appropriate for
+    /// scalars that are not secret, such as the group order `\ell` used
+    /// in torsion checks.
+    pub(crate) fn mul_bits_vartime(&self, bytes: &[u8; 32]) -> ExtendedPoint {
+        let mut result = ExtendedPoint::identity();
+        for i in (0..256).rev() {
+            result = result.double();
+            let bit = (bytes[i / 8] >> (i % 8)) & 1;
+            if bit == 1 {
+                result = result.add(self);
+            }
+        }
+        result
+    }
+
+    /// Multiply this point by the cofactor `8`, via three doublings.
+    pub fn mul_by_cofactor(&self) -> ExtendedPoint {
+        self.double().double().double()
+    }
+
+    /// Determine if this point is of small order, i.e. whether it lies
+    /// entirely within the 8-torsion subgroup `Ɛ[8]`.
+    ///
+    /// This should be checked on any externally-supplied point before
+    /// using it in a protocol that assumes the prime-order subgroup,
+    /// such as key agreement or signature verification, to reject
+    /// small-subgroup confinement attacks.
+    pub fn is_small_order(&self) -> bool {
+        self.mul_by_cofactor() == ExtendedPoint::identity()
+    }
+
+    /// Determine if this point is "torsion-free", i.e. whether it lies
+    /// in the prime-order subgroup generated by the basepoint, with no
+    /// component in the 8-torsion subgroup `Ɛ[8]`.
+    ///
+    /// This multiplies the point by the group order `\ell`; since `\ell`
+    /// is public, the variable-time ladder in `mul_bits_vartime` is an
+    /// acceptable implementation.
+    pub fn is_torsion_free(&self) -> bool {
+        self.mul_bits_vartime(&GROUP_ORDER) == ExtendedPoint::identity()
+    }
+
+    /// Convert to the `u`-coordinate of the corresponding point on the
+    /// birationally-equivalent Montgomery curve, via `u = (1+y)/(1-y)`.
+    pub fn to_montgomery(&self) -> MontgomeryPoint {
+        let u = &(&self.Z + &self.Y) * &(&self.Z - &self.Y).invert();
+        MontgomeryPoint(u.to_bytes())
+    }
+
+    /// Double this point `k` times.
+    pub fn mul_by_pow_2(&self, k: u32) -> ExtendedPoint {
+        let mut r = *self;
+        for _ in 0..k {
+            r = r.double();
+        }
+        r
+    }
+
+    /// Convert to affine-Niels form `(y+x, y-x, 2d*x*y)`, the
+    /// precomputed representation stored in basepoint lookup tables,
+    /// which admits a cheaper mixed addition than adding two full
+    /// extended points.
+    fn to_affine_niels(&self) -> AffineNielsPoint {
+        let z_inv = self.Z.invert();
+        let x = &self.X * &z_inv;
+        let y = &self.Y * &z_inv;
+        let xy2d = &(&x * &y) * &EDWARDS_D2;
+
+        AffineNielsPoint {
+            y_plus_x: &y + &x,
+            y_minus_x: &y - &x,
+            xy2d,
+        }
+    }
+
+    /// Add an affine-Niels point to this point, using a cheaper mixed
+    /// addition formula than `add` (which requires both inputs to carry
+    /// their own `Z`).
+    pub(crate) fn add_affine_niels(&self, other: &AffineNielsPoint) -> ExtendedPoint {
+        let y_plus_x = &self.Y + &self.X;
+        let y_minus_x = &self.Y - &self.X;
+
+        let pp = &y_plus_x * &other.y_plus_x;
+        let mm = &y_minus_x * &other.y_minus_x;
+        let txy2d = &self.T * &other.xy2d;
+        let zz2 = &self.Z + &self.Z;
+
+        let e = &pp - &mm;
+        let f = &zz2 - &txy2d;
+        let g = &zz2 + &txy2d;
+        let h = &pp + &mm;
+
+        ExtendedPoint {
+            X: &e * &f,
+            Y: &g * &h,
+            Z: &f * &g,
+            T: &e * &h,
+        }
+    }
+}
+
+/// A point on Curve25519, precomputed in affine-Niels form
+/// `(y+x, y-x, 2d*x*y)` for use in basepoint lookup tables.
+#[derive(Copy, Clone, Debug)]
+#[allow(non_snake_case)]
+pub(crate) struct AffineNielsPoint {
+    y_plus_x: FieldElement64,
+    y_minus_x: FieldElement64,
+    xy2d: FieldElement64,
+}
+
+impl AffineNielsPoint {
+    fn identity() -> AffineNielsPoint {
+        AffineNielsPoint {
+            y_plus_x: FieldElement64::one(),
+            y_minus_x: FieldElement64::one(),
+            xy2d: FieldElement64::zero(),
+        }
+    }
+
+    /// Negate this point: `(y+x, y-x, 2d*x*y) -> (y-x, y+x, -2d*x*y)`.
+    fn negate(&self) -> AffineNielsPoint {
+        AffineNielsPoint {
+            y_plus_x: self.y_minus_x,
+            y_minus_x: self.y_plus_x,
+            xy2d: -&self.xy2d,
+        }
+    }
+
+    /// Conditionally overwrite `self` with `other` in constant time,
+    /// using the masked field-element selection from `field_64bit`.
+    fn conditional_assign(&mut self, other: &AffineNielsPoint, choice: u8) {
+        self.y_plus_x.conditional_assign(&other.y_plus_x, choice);
+        self.y_minus_x.conditional_assign(&other.y_minus_x, choice);
+        self.xy2d.conditional_assign(&other.xy2d, choice);
+    }
+}
+
+/// Returns `1u8` if `a == b`, and `0u8` otherwise, computed without a
+/// data-dependent branch.
+fn bytes_equal_ct(a: u8, b: u8) -> u8 {
+    let x = (a ^ b) as u32;
+    let is_nonzero = ((x | x.wrapping_neg()) >> 31) as u8;
+    1u8 - is_nonzero
+}
+
+/// A table of the points `[1B, 2B, ..., 8B]`, for some point `B`, stored
+/// in affine-Niels form, supporting masked constant-time lookup by a
+/// signed digit in `[-8, 8]`.
+#[derive(Copy, Clone)]
+pub(crate) struct LookupTable([AffineNielsPoint; 8]);
+
+impl LookupTable {
+    fn from_point(point: &ExtendedPoint) -> LookupTable {
+        let mut points = [point.to_affine_niels(); 8];
+        for i in 1..8 {
+            points[i] = point.add_affine_niels(&points[i - 1]).to_affine_niels();
+        }
+        LookupTable(points)
+    }
+
+    /// Select `x*B` in constant time, for `x` in `[-8, 8]`, accessing
+    /// every table entry regardless of `x` so that memory access
+    /// patterns do not leak the digit.
+    fn select(&self, x: i8) -> AffineNielsPoint {
+        debug_assert!((-8..=8).contains(&x));
+
+        let sign_mask = (x >> 7) as u8; // all-1s if x < 0, else 0
+        let x_abs = (x as i16).unsigned_abs() as u8;
+
+        let mut result = AffineNielsPoint::identity();
+        for j in 1..9u8 {
+            let choice = bytes_equal_ct(x_abs, j);
+            result.conditional_assign(&self.0[(j - 1) as usize], choice);
+        }
+
+        let negated = result.negate();
+        result.conditional_assign(&negated, sign_mask & 1);
+        result
+    }
+}
+
+/// A precomputed table for fast fixed-base scalar multiplication
+/// `scalar * B`, built from `32` per-byte `LookupTable`s of the form
+/// `[1, 2, ..., 8] * (256^i * B)`.
+#[derive(Copy, Clone)]
+pub struct EdwardsBasepointTable(pub(crate) [LookupTable; 32]);
+
+impl EdwardsBasepointTable {
+    /// Build a basepoint table from `basepoint`, by repeated doubling
+    /// to compute `256^i * basepoint` for `i` in `0..32`, and then
+    /// repeated addition to fill in each table's eight multiples.
+    pub fn create(basepoint: &ExtendedPoint) -> EdwardsBasepointTable {
+        let mut multiples_of_basepoint = [*basepoint; 32];
+        for i in 1..32 {
+            multiples_of_basepoint[i] = multiples_of_basepoint[i - 1].mul_by_pow_2(8);
+        }
+
+        let mut tables = [LookupTable::from_point(basepoint); 32];
+        for i in 0..32 {
+            tables[i] = LookupTable::from_point(&multiples_of_basepoint[i]);
+        }
+
+        EdwardsBasepointTable(tables)
+    }
+}
+
+impl<'a, 'b> Mul<&'b Scalar> for &'a EdwardsBasepointTable {
+    type Output = ExtendedPoint;
+
+    /// Compute `scalar * B` in roughly constant time, using the
+    /// signed-radix-16 digits of `scalar` to select precomputed
+    /// multiples of `256^i * B` from each of the table's 32 entries.
+    fn mul(self, scalar: &'b Scalar) -> ExtendedPoint {
+        let digits = scalar.to_radix_16();
+
+        let mut result = ExtendedPoint::identity();
+        for i in 0..32 {
+            let lo = self.0[i].select(digits[2 * i]);
+            result = result.add_affine_niels(&lo);
+
+            let hi = self.0[i].select(digits[2 * i + 1]);
+            let hi_point = ExtendedPoint::identity()
+                .add_affine_niels(&hi)
+                .mul_by_pow_2(4);
+            result = result.add(&hi_point);
+        }
+
+        result
+    }
+}
+
+impl<'a> Add<&'a ExtendedPoint> for &'a ExtendedPoint {
+    type Output = ExtendedPoint;
+    fn add(self, other: &'a ExtendedPoint) -> ExtendedPoint {
+        ExtendedPoint::add(self, other)
+    }
+}
+
+impl<'a> Neg for &'a ExtendedPoint {
+    type Output = ExtendedPoint;
+    fn neg(self) -> ExtendedPoint {
+        self.negate()
+    }
+}
+
+impl PartialEq for ExtendedPoint {
+    /// Test equality of two points by comparing their affine coordinates,
+    /// computed from the projective `(X:Y:Z)` representations.
+    fn eq(&self, other: &ExtendedPoint) -> bool {
+        (&self.X * &other.Z) == (&other.X * &self.Z)
+            && (&self.Y * &other.Z) == (&other.Y * &self.Z)
+    }
+}
+
+impl Eq for ExtendedPoint {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use constants_64bit::{EIGHT_TORSION, ED25519_BASEPOINT_POINT};
+
+    #[test]
+    fn double_matches_add_to_self() {
+        let b = ED25519_BASEPOINT_POINT;
+        assert_eq!(b.double(), b.add(&b));
+    }
+
+    #[test]
+    fn basepoint_double_cubed_is_cofactor_mul() {
+        let b = ED25519_BASEPOINT_POINT;
+        assert_eq!(b.double().double().double(), b.mul_by_cofactor());
+    }
+
+    #[test]
+    fn basepoint_is_torsion_free() {
+        assert!(ED25519_BASEPOINT_POINT.is_torsion_free());
+        assert!(!ED25519_BASEPOINT_POINT.is_small_order());
+    }
+
+    #[test]
+    fn eight_torsion_points_are_small_order() {
+        for point in EIGHT_TORSION.iter() {
+            assert!(point.is_small_order());
+        }
+    }
+
+    #[test]
+    fn negate_is_involution() {
+        let b = ED25519_BASEPOINT_POINT;
+        assert_eq!(b.negate().negate(), b);
+    }
+
+    #[test]
+    fn identity_is_additive_identity() {
+        let b = ED25519_BASEPOINT_POINT;
+        assert_eq!(b.add(&ExtendedPoint::identity()), b);
+    }
+}
@@ -0,0 +1,511 @@
+// -*- mode: rust; -*-
+//
+// This file is part of curve25519-dalek.
+// Copyright (c) 2016-2017 Isis Lovecruft, Henry de Valence
+// See LICENSE for licensing information.
+//
+// Authors:
+// - Isis Agora Lovecruft <isis@patternsinthevoid.net>
+// - Henry de Valence <hdevalence@hdevalence.ca>
+
+//! Field arithmetic modulo \\(p = 2\^{255} - 19\\), using \\(64\\)-bit
+//! limbs with \\(128\\)-bit products, in radix \\(2\^{51}\\).
+//!
+//! This code is a port of the exhaustively optimised field arithmetic from
+//! `ref10`, rewritten to use Rust's `u128` type for the double-width
+//! intermediate products.
+
+use std::ops::{Add, AddAssign};
+use std::ops::{Sub, SubAssign};
+use std::ops::{Mul, MulAssign};
+use std::ops::Neg;
+
+use constants_64bit::SQRT_M1;
+
+/// A `FieldElement64` represents an element of the field
+/// \\( \mathbb Z / (2\^{255} - 19)\\).
+///
+/// In the 64-bit implementation, a `FieldElement64` is represented in
+/// radix \\(2\^{51}\\) as five `u64`s, so that an element \\(t\\),
+/// entries \\(t[0], \ldots ,t[4]\\), represents the integer
+/// \\(t = \sum_{i=0}^{4} t[i] 2^{51 i}\\).
+///
+/// The coefficients are allowed to grow between reductions up to
+/// \\(2\^{54}\\); generic bounds are not otherwise tracked, since the
+/// arithmetic below reduces eagerly enough for correctness.
+#[derive(Copy, Clone, Debug)]
+pub struct FieldElement64(pub [u64; 5]);
+
+/// `2^51 - 1`, the bitmask for a 51-bit limb.
+const LOW_51_BIT_MASK: u64 = (1u64 << 51) - 1;
+
+impl FieldElement64 {
+    /// The additive identity.
+    pub fn zero() -> FieldElement64 {
+        FieldElement64([0, 0, 0, 0, 0])
+    }
+
+    /// The multiplicative identity.
+    pub fn one() -> FieldElement64 {
+        FieldElement64([1, 0, 0, 0, 0])
+    }
+
+    /// The additive inverse of the multiplicative identity, i.e. `-1 mod p`.
+    pub fn minus_one() -> FieldElement64 {
+        FieldElement64([
+            2251799813685228,
+            2251799813685247,
+            2251799813685247,
+            2251799813685247,
+            2251799813685247,
+        ])
+    }
+
+    /// Given the limbs of an accumulator (as produced by `Mul`, which can
+    /// run up to roughly `2^108` before reduction), carry them down to
+    /// fit the `2^51` bound.
+    fn reduce(mut limbs: [u128; 5]) -> FieldElement64 {
+        const LOW_51_BIT_MASK: u128 = (1u128 << 51) - 1;
+
+        // The carry out of limb `i` must be folded into limb `i+1`
+        // *before* limb `i+1`'s own carry is computed, since limb `i+1`
+        // may itself already be large enough to need it; a single
+        // parallel pass only carries correctly for inputs already
+        // bounded by about `2^64`, which the real ~2^108 accumulators
+        // from `Mul` are not.
+        let c0 = (limbs[0] >> 51) as u64;
+        limbs[0] &= LOW_51_BIT_MASK;
+        limbs[1] += c0 as u128;
+
+        let c1 = (limbs[1] >> 51) as u64;
+        limbs[1] &= LOW_51_BIT_MASK;
+        limbs[2] += c1 as u128;
+
+        let c2 = (limbs[2] >> 51) as u64;
+        limbs[2] &= LOW_51_BIT_MASK;
+        limbs[3] += c2 as u128;
+
+        let c3 = (limbs[3] >> 51) as u64;
+        limbs[3] &= LOW_51_BIT_MASK;
+        limbs[4] += c3 as u128;
+
+        // Since 2^255 = 19 (mod p), the carry out of the last limb wraps
+        // back around to the first, multiplied by 19.
+        let c4 = (limbs[4] >> 51) as u64;
+        limbs[4] &= LOW_51_BIT_MASK;
+        limbs[0] += (c4 as u128) * 19;
+
+        // That wraparound addition can itself overflow limb 0's bound,
+        // so carry once more.
+        let c0 = (limbs[0] >> 51) as u64;
+        limbs[0] &= LOW_51_BIT_MASK;
+        limbs[1] += c0 as u128;
+
+        FieldElement64([
+            limbs[0] as u64,
+            limbs[1] as u64,
+            limbs[2] as u64,
+            limbs[3] as u64,
+            limbs[4] as u64,
+        ])
+    }
+
+    /// Load a `FieldElement64` from the low 255 bits of a 256-bit input.
+    pub fn from_bytes(bytes: &[u8; 32]) -> FieldElement64 {
+        let load8 = |input: &[u8]| -> u64 {
+            (input[0] as u64)
+                | ((input[1] as u64) << 8)
+                | ((input[2] as u64) << 16)
+                | ((input[3] as u64) << 24)
+                | ((input[4] as u64) << 32)
+                | ((input[5] as u64) << 40)
+                | ((input[6] as u64) << 48)
+                | ((input[7] as u64) << 56)
+        };
+
+        let low_51_bit_mask = (1u64 << 51) - 1;
+        FieldElement64([
+            load8(&bytes[0..]) & low_51_bit_mask,
+            (load8(&bytes[6..]) >> 3) & low_51_bit_mask,
+            (load8(&bytes[12..]) >> 6) & low_51_bit_mask,
+            (load8(&bytes[19..]) >> 1) & low_51_bit_mask,
+            (load8(&bytes[24..]) >> 12) & low_51_bit_mask,
+        ])
+    }
+
+    /// Serialize this `FieldElement64` to a 32-byte array, reducing it
+    /// mod \\(p\\) first so that the encoding is canonical.
+    pub fn to_bytes(&self) -> [u8; 32] {
+        // Let h = limbs[0] + limbs[1]*2^51 + ... + limbs[4]*2^204.
+        //
+        // First, reduce the limbs so that 0 <= h < 2*p, then conditionally
+        // subtract p to bring h fully into [0, p).
+        let mut limbs = self.0;
+
+        // Carry the chain once more so that each limb is < 2^51, and the
+        // whole value is < 2^255 + something tiny.
+        let mut carry = 0u64;
+        for i in 0..5 {
+            limbs[i] += carry;
+            carry = limbs[i] >> 51;
+            limbs[i] &= LOW_51_BIT_MASK;
+        }
+        limbs[0] += carry * 19;
+        carry = limbs[0] >> 51;
+        limbs[0] &= LOW_51_BIT_MASK;
+        limbs[1] += carry;
+
+        // Now h may be in [0, 2p). Determine if a final subtraction of p
+        // is necessary by computing h + 19, then checking for a carry out
+        // of bit 255.
+        let mut q = (limbs[0] + 19) >> 51;
+        q = (limbs[1] + q) >> 51;
+        q = (limbs[2] + q) >> 51;
+        q = (limbs[3] + q) >> 51;
+        q = (limbs[4] + q) >> 51;
+
+        limbs[0] += 19 * q;
+
+        carry = limbs[0] >> 51;
+        limbs[0] &= LOW_51_BIT_MASK;
+        limbs[1] += carry;
+        carry = limbs[1] >> 51;
+        limbs[1] &= LOW_51_BIT_MASK;
+        limbs[2] += carry;
+        carry = limbs[2] >> 51;
+        limbs[2] &= LOW_51_BIT_MASK;
+        limbs[3] += carry;
+        carry = limbs[3] >> 51;
+        limbs[3] &= LOW_51_BIT_MASK;
+        limbs[4] += carry;
+        limbs[4] &= LOW_51_BIT_MASK;
+
+        // Now pack the 5x51-bit limbs into 32 bytes.
+        let mut s = [0u8; 32];
+        s[0] = limbs[0] as u8;
+        s[1] = (limbs[0] >> 8) as u8;
+        s[2] = (limbs[0] >> 16) as u8;
+        s[3] = (limbs[0] >> 24) as u8;
+        s[4] = (limbs[0] >> 32) as u8;
+        s[5] = (limbs[0] >> 40) as u8;
+        s[6] = ((limbs[0] >> 48) | (limbs[1] << 3)) as u8;
+        s[7] = (limbs[1] >> 5) as u8;
+        s[8] = (limbs[1] >> 13) as u8;
+        s[9] = (limbs[1] >> 21) as u8;
+        s[10] = (limbs[1] >> 29) as u8;
+        s[11] = (limbs[1] >> 37) as u8;
+        s[12] = ((limbs[1] >> 45) | (limbs[2] << 6)) as u8;
+        s[13] = (limbs[2] >> 2) as u8;
+        s[14] = (limbs[2] >> 10) as u8;
+        s[15] = (limbs[2] >> 18) as u8;
+        s[16] = (limbs[2] >> 26) as u8;
+        s[17] = (limbs[2] >> 34) as u8;
+        s[18] = (limbs[2] >> 42) as u8;
+        s[19] = ((limbs[2] >> 50) | (limbs[3] << 1)) as u8;
+        s[20] = (limbs[3] >> 7) as u8;
+        s[21] = (limbs[3] >> 15) as u8;
+        s[22] = (limbs[3] >> 23) as u8;
+        s[23] = (limbs[3] >> 31) as u8;
+        s[24] = (limbs[3] >> 39) as u8;
+        s[25] = ((limbs[3] >> 47) | (limbs[4] << 4)) as u8;
+        s[26] = (limbs[4] >> 4) as u8;
+        s[27] = (limbs[4] >> 12) as u8;
+        s[28] = (limbs[4] >> 20) as u8;
+        s[29] = (limbs[4] >> 28) as u8;
+        s[30] = (limbs[4] >> 36) as u8;
+        s[31] = (limbs[4] >> 44) as u8;
+
+        s
+    }
+
+    /// Determine if this `FieldElement64` is negative, in the sense
+    /// used for Edwards-curve point compression: `x` is negative if the
+    /// least significant bit of its canonical encoding is `1`.
+    pub fn is_negative(&self) -> bool {
+        let bytes = self.to_bytes();
+        (bytes[0] & 1) == 1
+    }
+
+    /// Determine if this `FieldElement64` is equal to zero.
+    pub fn is_zero(&self) -> bool {
+        self.to_bytes() == [0u8; 32]
+    }
+
+    /// Compute `self^(2^k)` by repeated squaring.
+    pub fn pow2k(&self, k: u32) -> FieldElement64 {
+        debug_assert!(k > 0);
+        let mut z = self.square();
+        for _ in 1..k {
+            z = z.square();
+        }
+        z
+    }
+
+    /// Compute `self^2`.
+    pub fn square(&self) -> FieldElement64 {
+        self * self
+    }
+
+    /// Compute `2*self^2`.
+    pub fn square2(&self) -> FieldElement64 {
+        let s = self.square();
+        &s + &s
+    }
+
+    /// Given `self`, compute `self^(2^250 - 1)` and `self^11`. This
+    /// partial exponentiation is shared by `invert` and `invsqrt`.
+    fn pow22501(&self) -> (FieldElement64, FieldElement64) {
+        let t0 = self.square();
+        let t1 = t0.square().square();
+        let t2 = self * &t1;
+        let t3 = &t0 * &t2;
+        let t4 = t3.square();
+        let t5 = &t2 * &t4;
+        let t6 = t5.pow2k(5);
+        let t7 = &t6 * &t5;
+        let t8 = t7.pow2k(10);
+        let t9 = &t8 * &t7;
+        let t10 = t9.pow2k(20);
+        let t11 = &t10 * &t9;
+        let t12 = t11.pow2k(10);
+        let t13 = &t12 * &t7;
+        let t14 = t13.pow2k(50);
+        let t15 = &t14 * &t13;
+        let t16 = t15.pow2k(100);
+        let t17 = &t16 * &t15;
+        let t18 = t17.pow2k(50);
+        let t19 = &t18 * &t13;
+
+        (t19, t3)
+    }
+
+    /// Compute `self^-1 mod p` using Fermat's little theorem.
+    pub fn invert(&self) -> FieldElement64 {
+        let (t19, t3) = self.pow22501();
+        let t20 = t19.pow2k(5);
+        &t20 * &t3
+    }
+
+    /// Compute the inverse square root of a field element.
+    ///
+    /// # Return
+    ///
+    /// - `(true,  r)` with `r^2 * self = 1` if `self` is a nonzero square;
+    /// - `(false, r)` with `r^2 * self = i` or `-i` if `self` is a nonzero
+    ///   nonsquare, where `i = SQRT_M1` is a fixed square root of `-1`.
+    ///
+    /// The sign of `r` is not normalised: it may be either root.
+    ///
+    /// The boolean in the first component of the tuple is computed by
+    /// comparing field elements, rather than in constant time; callers
+    /// who need a timing-independent check should compare the
+    /// canonical encodings of the returned value against the expected
+    /// one themselves.
+    pub fn invsqrt(&self) -> (bool, FieldElement64) {
+        let (t19, _t3) = self.pow22501();
+
+        // t19 = self^(2^250 - 1), so t19.pow2k(2) * self = self^(2^252 - 3),
+        // which is the Legendre-symbol-style exponent used to extract an
+        // inverse square root when p = 5 (mod 8).
+        let r = &t19.pow2k(2) * self;
+        let check = &r.square() * self;
+
+        let correct_sign = check == FieldElement64::one();
+        let flipped_sign = check == -&FieldElement64::one();
+
+        let result = if flipped_sign { &SQRT_M1 * &r } else { r };
+
+        (correct_sign || flipped_sign, result)
+    }
+
+    /// Conditionally swap `self` and `other`, in constant time.
+    ///
+    /// `choice` must be `0` or `1`; the swap is performed by masking
+    /// rather than branching, so that the choice bit cannot leak
+    /// through a secret-dependent branch.
+    pub fn conditional_swap(&mut self, other: &mut FieldElement64, choice: u8) {
+        let mask = 0u64.wrapping_sub(choice as u64);
+        for i in 0..5 {
+            let t = mask & (self.0[i] ^ other.0[i]);
+            self.0[i] ^= t;
+            other.0[i] ^= t;
+        }
+    }
+
+    /// Conditionally overwrite `self` with `other`, in constant time.
+    ///
+    /// `choice` must be `0` or `1`; as with `conditional_swap`, the
+    /// assignment is performed by masking rather than branching.
+    pub fn conditional_assign(&mut self, other: &FieldElement64, choice: u8) {
+        let mask = 0u64.wrapping_sub(choice as u64);
+        for i in 0..5 {
+            self.0[i] ^= mask & (self.0[i] ^ other.0[i]);
+        }
+    }
+}
+
+impl<'a, 'b> Add<&'b FieldElement64> for &'a FieldElement64 {
+    type Output = FieldElement64;
+    fn add(self, rhs: &'b FieldElement64) -> FieldElement64 {
+        let mut output = self.0;
+        for i in 0..5 {
+            output[i] += rhs.0[i];
+        }
+        FieldElement64(output)
+    }
+}
+
+impl AddAssign for FieldElement64 {
+    fn add_assign(&mut self, rhs: FieldElement64) {
+        *self = &*self + &rhs;
+    }
+}
+
+impl<'a, 'b> Sub<&'b FieldElement64> for &'a FieldElement64 {
+    type Output = FieldElement64;
+    fn sub(self, rhs: &'b FieldElement64) -> FieldElement64 {
+        // To avoid underflow, add `16*p` (written limb by limb as
+        // `16*(2^51 - 19)` and four `16*(2^51 - 1)`) before subtracting.
+        let l = [
+            36028797018963664u64,
+            36028797018963952,
+            36028797018963952,
+            36028797018963952,
+            36028797018963952,
+        ];
+        let mut output = [0u64; 5];
+        for i in 0..5 {
+            output[i] = (self.0[i] + l[i]) - rhs.0[i];
+        }
+        FieldElement64::reduce([
+            output[0] as u128,
+            output[1] as u128,
+            output[2] as u128,
+            output[3] as u128,
+            output[4] as u128,
+        ])
+    }
+}
+
+impl SubAssign for FieldElement64 {
+    fn sub_assign(&mut self, rhs: FieldElement64) {
+        *self = &*self - &rhs;
+    }
+}
+
+impl<'a, 'b> Mul<&'b FieldElement64> for &'a FieldElement64 {
+    type Output = FieldElement64;
+    fn mul(self, rhs: &'b FieldElement64) -> FieldElement64 {
+        let a = &self.0;
+        let b = &rhs.0;
+
+        // Multiply by 19, promoted to u128 to avoid overflow in the
+        // cross terms coming from the `2^255 = 19` reduction.
+        let b1_19 = b[1] * 19;
+        let b2_19 = b[2] * 19;
+        let b3_19 = b[3] * 19;
+        let b4_19 = b[4] * 19;
+
+        let c0 = (a[0] as u128) * (b[0] as u128)
+            + (a[1] as u128) * (b4_19 as u128)
+            + (a[2] as u128) * (b3_19 as u128)
+            + (a[3] as u128) * (b2_19 as u128)
+            + (a[4] as u128) * (b1_19 as u128);
+
+        let c1 = (a[0] as u128) * (b[1] as u128)
+            + (a[1] as u128) * (b[0] as u128)
+            + (a[2] as u128) * (b4_19 as u128)
+            + (a[3] as u128) * (b3_19 as u128)
+            + (a[4] as u128) * (b2_19 as u128);
+
+        let c2 = (a[0] as u128) * (b[2] as u128)
+            + (a[1] as u128) * (b[1] as u128)
+            + (a[2] as u128) * (b[0] as u128)
+            + (a[3] as u128) * (b4_19 as u128)
+            + (a[4] as u128) * (b3_19 as u128);
+
+        let c3 = (a[0] as u128) * (b[3] as u128)
+            + (a[1] as u128) * (b[2] as u128)
+            + (a[2] as u128) * (b[1] as u128)
+            + (a[3] as u128) * (b[0] as u128)
+            + (a[4] as u128) * (b4_19 as u128);
+
+        let c4 = (a[0] as u128) * (b[4] as u128)
+            + (a[1] as u128) * (b[3] as u128)
+            + (a[2] as u128) * (b[2] as u128)
+            + (a[3] as u128) * (b[1] as u128)
+            + (a[4] as u128) * (b[0] as u128);
+
+        FieldElement64::reduce([c0, c1, c2, c3, c4])
+    }
+}
+
+impl MulAssign for FieldElement64 {
+    fn mul_assign(&mut self, rhs: FieldElement64) {
+        *self = &*self * &rhs;
+    }
+}
+
+impl<'a> Neg for &'a FieldElement64 {
+    type Output = FieldElement64;
+    fn neg(self) -> FieldElement64 {
+        &FieldElement64::zero() - self
+    }
+}
+
+impl PartialEq for FieldElement64 {
+    fn eq(&self, other: &FieldElement64) -> bool {
+        self.to_bytes() == other.to_bytes()
+    }
+}
+
+impl Eq for FieldElement64 {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_bytes_to_bytes_roundtrip() {
+        let bytes = [
+            1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24,
+            25, 26, 27, 28, 29, 30, 31, 0,
+        ];
+        let fe = FieldElement64::from_bytes(&bytes);
+        assert_eq!(fe.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn invert_of_nine_is_correct() {
+        let mut bytes = [0u8; 32];
+        bytes[0] = 9;
+        let nine = FieldElement64::from_bytes(&bytes);
+        let inverse = nine.invert();
+        assert_eq!(&nine * &inverse, FieldElement64::one());
+    }
+
+    #[test]
+    fn square_matches_self_times_self() {
+        let mut bytes = [0u8; 32];
+        bytes[0] = 123;
+        bytes[5] = 45;
+        let x = FieldElement64::from_bytes(&bytes);
+        assert_eq!(x.square(), &x * &x);
+    }
+
+    #[test]
+    fn minus_one_is_additive_inverse_of_one() {
+        let sum = &FieldElement64::one() + &FieldElement64::minus_one();
+        assert_eq!(sum, FieldElement64::zero());
+    }
+
+    #[test]
+    fn invsqrt_of_a_square_is_correct() {
+        let mut bytes = [0u8; 32];
+        bytes[0] = 4;
+        let four = FieldElement64::from_bytes(&bytes);
+        let (is_square, root) = four.invsqrt();
+        assert!(is_square);
+        assert_eq!(&root.square() * &four, FieldElement64::one());
+    }
+}
@@ -0,0 +1,28 @@
+// -*- mode: rust; -*-
+//
+// This file is part of curve25519-dalek.
+// Copyright (c) 2016-2017 Isis Lovecruft, Henry de Valence
+// See LICENSE for licensing information.
+//
+// Authors:
+// - Isis Agora Lovecruft <isis@patternsinthevoid.net>
+// - Henry de Valence <hdevalence@hdevalence.ca>
+
+//! # curve25519-dalek
+//!
+//! A pure-Rust implementation of group operations on Curve25519.
+
+// The arithmetic below is a close port of the `ref10` C implementation,
+// which favours explicit lifetimes on by-reference operator impls and
+// indexing loops over fixed-size limb arrays; both read more clearly
+// here than the iterator-based alternatives clippy suggests.
+#![allow(clippy::needless_lifetimes)]
+#![allow(clippy::needless_range_loop)]
+#![allow(clippy::wrong_self_convention)]
+
+pub mod constants_64bit;
+pub mod field_64bit;
+pub mod scalar;
+pub mod edwards;
+pub mod montgomery;
+pub mod ristretto;
@@ -0,0 +1,128 @@
+// -*- mode: rust; -*-
+//
+// This file is part of curve25519-dalek.
+// Copyright (c) 2016-2017 Isis Lovecruft, Henry de Valence
+// See LICENSE for licensing information.
+//
+// Authors:
+// - Isis Agora Lovecruft <isis@patternsinthevoid.net>
+// - Henry de Valence <hdevalence@hdevalence.ca>
+
+//! Group operations for Curve25519 in Montgomery form, using only the
+//! `u`-coordinate. This gives a native implementation of the X25519
+//! Diffie-Hellman function, without going through the Edwards form.
+
+use constants_64bit::{MONTGOMERY_A, MONTGOMERY_A24, SQRT_MINUS_APLUS2};
+use edwards::ExtendedPoint;
+use field_64bit::FieldElement64;
+use scalar::Scalar;
+
+/// Holds the `u`-coordinate of a point on the Montgomery form of
+/// Curve25519, as a 32-byte little-endian field element encoding.
+#[derive(Copy, Clone, Debug)]
+pub struct MontgomeryPoint(pub [u8; 32]);
+
+impl MontgomeryPoint {
+    /// Compute `scalar * self` using the Montgomery ladder, the
+    /// standard X25519 scalar multiplication algorithm.
+    pub fn mul(&self, scalar: &Scalar) -> MontgomeryPoint {
+        let x1 = FieldElement64::from_bytes(&self.0);
+        let mut x2 = FieldElement64::one();
+        let mut z2 = FieldElement64::zero();
+        let mut x3 = x1;
+        let mut z3 = FieldElement64::one();
+        let mut swap: u8 = 0;
+
+        let bits = scalar.bytes;
+        for i in (0..255).rev() {
+            let bit = (bits[i >> 3] >> (i & 7)) & 1;
+            let choice = swap ^ bit;
+            x2.conditional_swap(&mut x3, choice);
+            z2.conditional_swap(&mut z3, choice);
+            swap = bit;
+
+            // A single ladder step, as in RFC 7748 §5.
+            let a = &x2 + &z2;
+            let aa = a.square();
+            let b = &x2 - &z2;
+            let bb = b.square();
+            let e = &aa - &bb;
+            let c = &x3 + &z3;
+            let d = &x3 - &z3;
+            let da = &d * &a;
+            let cb = &c * &b;
+            x3 = (&da + &cb).square();
+            z3 = &x1 * &(&da - &cb).square();
+            x2 = &aa * &bb;
+            z2 = &e * &(&aa + &(&MONTGOMERY_A24 * &e));
+        }
+        x2.conditional_swap(&mut x3, swap);
+        z2.conditional_swap(&mut z3, swap);
+
+        MontgomeryPoint((&x2 * &z2.invert()).to_bytes())
+    }
+
+    /// Recover the corresponding Edwards point, using the birational
+    /// map between Curve25519's Montgomery and twisted Edwards forms.
+    ///
+    /// Since a `MontgomeryPoint` only stores the `u`-coordinate, the
+    /// sign of the recovered Edwards `x`-coordinate is ambiguous; the
+    /// caller selects it via `sign`, which should be the low bit of the
+    /// `x`-coordinate of the point being recovered (e.g. as supplied by
+    /// an accompanying Ed25519 sign bit).
+    ///
+    /// Returns `None` if `self` is one of the two-torsion points, where
+    /// this map is undefined.
+    pub fn to_edwards(&self, sign: u8) -> Option<ExtendedPoint> {
+        let u = FieldElement64::from_bytes(&self.0);
+        let one = FieldElement64::one();
+
+        let u_minus_one = &u - &one;
+        let u_plus_one = &u + &one;
+        if u_plus_one.is_zero() {
+            return None;
+        }
+        let y = &u_minus_one * &u_plus_one.invert();
+
+        // The Montgomery curve equation v^2 = u^3 + A u^2 + u lets us
+        // recover v^2 (and hence x^2 = -(A+2) u^2 / v^2) from u alone.
+        let u2 = u.square();
+        let v2 = &u * &(&(&u2 + &(&MONTGOMERY_A * &u)) + &one);
+
+        let (was_square, inv_v) = v2.invsqrt();
+        if !was_square {
+            return None;
+        }
+
+        let mut x = &(&SQRT_MINUS_APLUS2 * &u) * &inv_v;
+        if x.is_negative() != (sign == 1) {
+            x = -&x;
+        }
+
+        let t = &x * &y;
+        Some(ExtendedPoint {
+            X: x,
+            Y: y,
+            Z: one,
+            T: t,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use constants_64bit::{ed25519_basepoint_table, ED25519_BASEPOINT_POINT};
+
+    #[test]
+    fn ladder_matches_edwards_basepoint_table() {
+        let mut bytes = [0u8; 32];
+        bytes[0] = 5;
+        let scalar = Scalar::from_bytes_mod_order(bytes);
+
+        let via_montgomery = ED25519_BASEPOINT_POINT.to_montgomery().mul(&scalar);
+        let via_edwards = (&ed25519_basepoint_table() * &scalar).to_montgomery();
+
+        assert_eq!(via_montgomery.0, via_edwards.0);
+    }
+}
@@ -0,0 +1,192 @@
+// -*- mode: rust; -*-
+//
+// This file is part of curve25519-dalek.
+// Copyright (c) 2016-2017 Isis Lovecruft, Henry de Valence
+// See LICENSE for licensing information.
+//
+// Authors:
+// - Isis Agora Lovecruft <isis@patternsinthevoid.net>
+// - Henry de Valence <hdevalence@hdevalence.ca>
+
+//! An implementation of Ristretto, which provides a prime-order group
+//! on top of a cofactor-8 curve, `Curve25519`.
+//!
+//! Internally, a `RistrettoPoint` is a wrapper around an `ExtendedPoint`
+//! representing a point in the equivalence class of points `P + E[8]` on
+//! the Edwards curve. Two `ExtendedPoint`s in the same equivalence class
+//! are encoded, compared, and decoded through the canonical
+//! representative singled out by the Ristretto encoding, so that
+//! `RistrettoPoint`s behave as elements of a prime-order group, with no
+//! exposed cofactor.
+
+use constants_64bit::{INVSQRT_A_MINUS_D, SQRT_AD_MINUS_ONE, SQRT_M1};
+use edwards::ExtendedPoint;
+use field_64bit::FieldElement64;
+
+/// A point serialized using Ristretto's canonical encoding.
+///
+/// Every `RistrettoPoint` has a single canonical 32-byte encoding, and
+/// every canonical encoding corresponds to at most one valid
+/// `RistrettoPoint`: unlike raw Edwards-point compression, distinct
+/// cofactor-8 representatives of the same Ristretto point are never
+/// given distinct encodings.
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub struct CompressedRistretto(pub [u8; 32]);
+
+impl CompressedRistretto {
+    /// View this `CompressedRistretto` as an array of bytes.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Attempt to decompress to a `RistrettoPoint`.
+    ///
+    /// Returns `None` if the input is not the canonical encoding of a
+    /// point, for instance because the encoded field element is not in
+    /// reduced form, because it is negative, or because the resulting
+    /// candidate point does not lie on the curve.
+    pub fn decompress(&self) -> Option<RistrettoPoint> {
+        let s = FieldElement64::from_bytes(&self.0);
+
+        // Reject non-canonical encodings of s.
+        if s.to_bytes() != self.0 {
+            return None;
+        }
+        // Reject negative s.
+        if s.is_negative() {
+            return None;
+        }
+
+        let one = FieldElement64::one();
+        let ss = s.square();
+        let u1 = &one - &ss;
+        let u2 = &one + &ss;
+        let u2_sqr = u2.square();
+
+        // v = a*d*u1^2 - u2^2, with a = -1.
+        //
+        // Writing d = -(SQRT_AD_MINUS_ONE^2) - 1 (since SQRT_AD_MINUS_ONE^2
+        // = a*d - 1 = -d - 1) gives v = (SQRT_AD_MINUS_ONE*u1)^2 - (2s)^2.
+        let w1 = &SQRT_AD_MINUS_ONE * &u1;
+        let two_s = &s + &s;
+        let v = &w1.square() - &two_s.square();
+
+        let (was_square, invsqrt) = (&v * &u2_sqr).invsqrt();
+
+        let den_x = &invsqrt * &u2;
+        let den_y = &invsqrt * &(&den_x * &v);
+
+        let mut x = &two_s * &den_x;
+        if x.is_negative() {
+            x = -&x;
+        }
+        let y = &u1 * &den_y;
+        let t = &x * &y;
+
+        if !was_square || t.is_negative() || y.is_zero() {
+            None
+        } else {
+            Some(RistrettoPoint(ExtendedPoint {
+                X: x,
+                Y: y,
+                Z: one,
+                T: t,
+            }))
+        }
+    }
+}
+
+/// A `RistrettoPoint` represents a point in the prime-order group
+/// obtained by taking the quotient of the cofactor-8 Edwards curve
+/// `Curve25519` by its 8-torsion subgroup, using the Ristretto encoding
+/// to pick a canonical representative of each coset.
+#[derive(Copy, Clone, Debug)]
+pub struct RistrettoPoint(pub(crate) ExtendedPoint);
+
+impl RistrettoPoint {
+    /// Compress this point to its canonical 32-byte encoding.
+    #[allow(non_snake_case)]
+    pub fn compress(&self) -> CompressedRistretto {
+        let X = &self.0.X;
+        let Y = &self.0.Y;
+        let Z = &self.0.Z;
+        let T = &self.0.T;
+
+        let u1 = &(Z + Y) * &(Z - Y);
+        let u2 = X * Y;
+
+        // I = invsqrt(u1 * u2^2). This value is always square, since it is
+        // (up to sign) the inverse of the product of two nonzero squares.
+        let (_, invsqrt) = (&u1 * &u2.square()).invsqrt();
+
+        let d1 = &u1 * &invsqrt;
+        let d2 = &u2 * &invsqrt;
+        let z_inv = &(&d1 * &d2) * T;
+
+        // If T*Zinv is negative, swap to the rotated representative
+        // (iY, iX) and rescale the denominator accordingly.
+        let mut x = *X;
+        let mut y = *Y;
+        let mut den = d2;
+        if (T * &z_inv).is_negative() {
+            x = Y * &SQRT_M1;
+            y = X * &SQRT_M1;
+            den = &d1 * &INVSQRT_A_MINUS_D;
+        }
+
+        // Conditionally negate Y so that X*Zinv is non-negative.
+        if (&x * &z_inv).is_negative() {
+            y = -&y;
+        }
+
+        let mut s = &den * &(Z - &y);
+        if s.is_negative() {
+            s = -&s;
+        }
+
+        CompressedRistretto(s.to_bytes())
+    }
+}
+
+impl PartialEq for RistrettoPoint {
+    /// Test equality of two `RistrettoPoint`s by comparing the affine
+    /// coordinates of their underlying `ExtendedPoint` representatives;
+    /// since these representatives may differ by an element of the
+    /// 8-torsion subgroup, this compares projective coordinates directly
+    /// rather than requiring `self.0 == other.0`.
+    fn eq(&self, other: &RistrettoPoint) -> bool {
+        let x1y2 = &self.0.X * &other.0.Y;
+        let y1x2 = &self.0.Y * &other.0.X;
+        let x1x2 = &self.0.X * &other.0.X;
+        let y1y2 = &self.0.Y * &other.0.Y;
+
+        (x1y2 == y1x2) || (x1x2 == y1y2)
+    }
+}
+
+impl Eq for RistrettoPoint {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use constants_64bit::ED25519_BASEPOINT_POINT;
+
+    #[test]
+    fn compress_decompress_roundtrip() {
+        let point = RistrettoPoint(ED25519_BASEPOINT_POINT);
+        let compressed = point.compress();
+        let decompressed = compressed.decompress().expect("valid encoding");
+        assert_eq!(point, decompressed);
+    }
+
+    #[test]
+    fn basepoint_compresses_to_known_encoding() {
+        let point = RistrettoPoint(ED25519_BASEPOINT_POINT);
+        let expected: [u8; 32] = [
+            0xe2, 0xf2, 0xae, 0x0a, 0x6a, 0xbc, 0x4e, 0x71, 0xa8, 0x84, 0xa9, 0x61, 0xc5, 0x00,
+            0x51, 0x5f, 0x58, 0xe3, 0x0b, 0x6a, 0xa5, 0x82, 0xdd, 0x8d, 0xb6, 0xa6, 0x59, 0x45,
+            0xe0, 0x8d, 0x2d, 0x76,
+        ];
+        assert_eq!(*point.compress().as_bytes(), expected);
+    }
+}
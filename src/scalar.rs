@@ -0,0 +1,339 @@
+// -*- mode: rust; -*-
+//
+// This file is part of curve25519-dalek.
+// Copyright (c) 2016-2017 Isis Lovecruft, Henry de Valence
+// See LICENSE for licensing information.
+//
+// Authors:
+// - Isis Agora Lovecruft <isis@patternsinthevoid.net>
+// - Henry de Valence <hdevalence@hdevalence.ca>
+
+//! Arithmetic modulo the basepoint order `\ell = 2^252 +
+//! 27742317777372353535851937790883648493`.
+//!
+//! Internally, scalars are represented in radix `2^52` as five `u64`
+//! limbs (`UnpackedScalar`), and multiplication is carried out with
+//! Montgomery's technique: a scalar `x` is converted into the
+//! Montgomery domain as `x*R mod \ell`, multiplied there in constant
+//! space via `montgomery_reduce`, and converted back.
+
+use std::ops::{Add, AddAssign, Mul, MulAssign, Sub, SubAssign};
+
+use constants_64bit::{L, LFACTOR, RR};
+
+/// A 52-bit limb mask.
+const MASK: u64 = (1u64 << 52) - 1;
+
+/// A `Scalar` holds an element of `Z/\ell` used to scale points on
+/// Curve25519, stored as the little-endian bytes of its representative
+/// in `[0, \ell)`.
+#[derive(Copy, Clone, Debug)]
+pub struct Scalar {
+    pub(crate) bytes: [u8; 32],
+}
+
+impl Scalar {
+    /// View this `Scalar` as its little-endian byte encoding.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.bytes
+    }
+
+    /// Write `self` as 64 signed digits in `[-8, 8]`, least-significant
+    /// first, such that `self = sum(digits[i] * 16^i)`. This is the
+    /// signed-radix-16 representation consumed by basepoint-table
+    /// scalar multiplication.
+    pub(crate) fn to_radix_16(&self) -> [i8; 64] {
+        let bytes = &self.bytes;
+        let mut output = [0i8; 64];
+
+        for i in 0..32 {
+            output[2 * i] = (bytes[i] & 0xf) as i8;
+            output[2 * i + 1] = ((bytes[i] >> 4) & 0xf) as i8;
+        }
+
+        // Recenter every digit but the last from [0, 16) to [-8, 8),
+        // carrying the excess into the next (more significant) digit.
+        for i in 0..63 {
+            let carry = (output[i] + 8) >> 4;
+            output[i] -= carry << 4;
+            output[i + 1] += carry;
+        }
+
+        output
+    }
+
+    /// Reduce a 256-bit little-endian integer modulo `\ell`.
+    pub fn from_bytes_mod_order(bytes: [u8; 32]) -> Scalar {
+        let x = UnpackedScalar::from_bytes(&bytes);
+
+        // Treat `x`'s limbs as the low half of a Montgomery product: one
+        // `montgomery_reduce` takes it out of Montgomery form (dividing
+        // by R), and multiplying the result by `RR` (which is `R^2`)
+        // brings it back to `x mod \ell` in normal form.
+        let reduced = UnpackedScalar::montgomery_reduce(&x.to_wide());
+        let result = UnpackedScalar::mont_mul(&reduced, &UnpackedScalar(RR));
+
+        Scalar {
+            bytes: result.to_bytes(),
+        }
+    }
+}
+
+impl<'a, 'b> Add<&'b Scalar> for &'a Scalar {
+    type Output = Scalar;
+    fn add(self, rhs: &'b Scalar) -> Scalar {
+        let a = UnpackedScalar::from_bytes(&self.bytes);
+        let b = UnpackedScalar::from_bytes(&rhs.bytes);
+        Scalar {
+            bytes: UnpackedScalar::add(&a, &b).to_bytes(),
+        }
+    }
+}
+
+impl AddAssign for Scalar {
+    fn add_assign(&mut self, rhs: Scalar) {
+        *self = &*self + &rhs;
+    }
+}
+
+impl<'a, 'b> Sub<&'b Scalar> for &'a Scalar {
+    type Output = Scalar;
+    fn sub(self, rhs: &'b Scalar) -> Scalar {
+        let a = UnpackedScalar::from_bytes(&self.bytes);
+        let b = UnpackedScalar::from_bytes(&rhs.bytes);
+        Scalar {
+            bytes: UnpackedScalar::sub(&a, &b).to_bytes(),
+        }
+    }
+}
+
+impl SubAssign for Scalar {
+    fn sub_assign(&mut self, rhs: Scalar) {
+        *self = &*self - &rhs;
+    }
+}
+
+impl<'a, 'b> Mul<&'b Scalar> for &'a Scalar {
+    type Output = Scalar;
+    fn mul(self, rhs: &'b Scalar) -> Scalar {
+        let a = UnpackedScalar::from_bytes(&self.bytes);
+        let b = UnpackedScalar::from_bytes(&rhs.bytes);
+        Scalar {
+            bytes: UnpackedScalar::mul(&a, &b).to_bytes(),
+        }
+    }
+}
+
+impl MulAssign for Scalar {
+    fn mul_assign(&mut self, rhs: Scalar) {
+        *self = &*self * &rhs;
+    }
+}
+
+/// The internal, small-limb representation of a `Scalar`: five `u64`
+/// limbs in radix `2^52`.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct UnpackedScalar(pub [u64; 5]);
+
+impl UnpackedScalar {
+    /// Unpack a 32-byte little-endian integer into radix-`2^52` limbs.
+    pub fn from_bytes(bytes: &[u8; 32]) -> UnpackedScalar {
+        let mut words = [0u64; 4];
+        for i in 0..4 {
+            for j in 0..8 {
+                words[i] |= (bytes[8 * i + j] as u64) << (8 * j);
+            }
+        }
+
+        let mut limbs = [0u64; 5];
+        limbs[0] = words[0] & MASK;
+        limbs[1] = ((words[0] >> 52) | (words[1] << 12)) & MASK;
+        limbs[2] = ((words[1] >> 40) | (words[2] << 24)) & MASK;
+        limbs[3] = ((words[2] >> 28) | (words[3] << 36)) & MASK;
+        limbs[4] = words[3] >> 16;
+
+        UnpackedScalar(limbs)
+    }
+
+    /// Repack radix-`2^52` limbs into a 32-byte little-endian integer.
+    pub fn to_bytes(&self) -> [u8; 32] {
+        let l = &self.0;
+        let mut words = [0u64; 4];
+        words[0] = l[0] | (l[1] << 52);
+        words[1] = (l[1] >> 12) | (l[2] << 40);
+        words[2] = (l[2] >> 24) | (l[3] << 28);
+        words[3] = (l[3] >> 36) | (l[4] << 16);
+
+        let mut bytes = [0u8; 32];
+        for i in 0..4 {
+            for j in 0..8 {
+                bytes[8 * i + j] = (words[i] >> (8 * j)) as u8;
+            }
+        }
+        bytes
+    }
+
+    /// Embed `self` as the low half of a 9-limb double-width value, for
+    /// use as the input to `montgomery_reduce`.
+    fn to_wide(&self) -> [u128; 9] {
+        let mut wide = [0u128; 9];
+        for i in 0..5 {
+            wide[i] = self.0[i] as u128;
+        }
+        wide
+    }
+
+    /// Schoolbook-multiply two scalars into a 9-limb double-width product.
+    fn mul_wide(a: &UnpackedScalar, b: &UnpackedScalar) -> [u128; 9] {
+        let mut products = [0u128; 9];
+        for i in 0..5 {
+            for j in 0..5 {
+                products[i + j] += (a.0[i] as u128) * (b.0[j] as u128);
+            }
+        }
+        products
+    }
+
+    /// Montgomery-reduce a 9-limb product modulo `\ell`, dividing out a
+    /// factor of `R = 2^260`.
+    ///
+    /// For each limb `i`, the reduction factor `m_i = (limbs[i] *
+    /// LFACTOR) mod 2^52` is chosen so that adding `m_i * L` clears the
+    /// low 52 bits of `limbs[i]`; the carry is then propagated into the
+    /// limbs above, and the (now zero) low half is discarded, leaving
+    /// the result divided by `R`.
+    pub fn montgomery_reduce(limbs: &[u128; 9]) -> UnpackedScalar {
+        let mut limbs = *limbs;
+
+        for i in 0..5 {
+            let m_i = ((limbs[i] as u64).wrapping_mul(LFACTOR)) & MASK;
+            for j in 0..5 {
+                limbs[i + j] += (m_i as u128) * (L[j] as u128);
+            }
+            let carry = limbs[i] >> 52;
+            limbs[i + 1] += carry;
+        }
+
+        // `limbs[4]` is now just a multiple of `2^52` left over from
+        // clearing its own low bits above (its value was already folded
+        // into `limbs[5]` by the carry inside the loop), so it must be
+        // discarded rather than read as part of the result: the
+        // `R`-divided product lives in `limbs[5..9]`, plus the final
+        // carry out of `limbs[8]`, which has no further limb to land in
+        // and so becomes the result's own top limb.
+        let mut result = [0u64; 5];
+        let mut carry = 0u128;
+        for i in 0..4 {
+            let sum = limbs[i + 5] + carry;
+            result[i] = (sum as u64) & MASK;
+            carry = sum >> 52;
+        }
+        result[4] = carry as u64;
+
+        UnpackedScalar(result).reduce_once()
+    }
+
+    /// Montgomery-multiply two scalars, producing `a*b*R^-1 mod \ell`.
+    pub fn mont_mul(a: &UnpackedScalar, b: &UnpackedScalar) -> UnpackedScalar {
+        UnpackedScalar::montgomery_reduce(&UnpackedScalar::mul_wide(a, b))
+    }
+
+    /// Multiply two scalars, producing `a*b mod \ell` in normal
+    /// (non-Montgomery) form, by round-tripping through the Montgomery
+    /// domain: `mont_mul(mont_mul(a, b), R^2) = a*b mod \ell`.
+    pub fn mul(a: &UnpackedScalar, b: &UnpackedScalar) -> UnpackedScalar {
+        UnpackedScalar::mont_mul(&UnpackedScalar::mont_mul(a, b), &UnpackedScalar(RR))
+    }
+
+    /// Add two scalars modulo `\ell`.
+    pub fn add(a: &UnpackedScalar, b: &UnpackedScalar) -> UnpackedScalar {
+        let mut sum = [0u64; 5];
+        let mut carry = 0u64;
+        for i in 0..5 {
+            carry = a.0[i] + b.0[i] + (carry >> 52);
+            sum[i] = carry & MASK;
+        }
+        UnpackedScalar(sum).reduce_once()
+    }
+
+    /// Subtract `b` from `a` modulo `\ell`.
+    pub fn sub(a: &UnpackedScalar, b: &UnpackedScalar) -> UnpackedScalar {
+        let mut difference = [0u64; 5];
+        let mut borrow = 0i64;
+        for i in 0..5 {
+            borrow = (a.0[i] as i64) - (b.0[i] as i64) - (borrow >> 52 & 1);
+            difference[i] = (borrow & (MASK as i64)) as u64;
+        }
+        // If the subtraction underflowed, add back `L`.
+        let underflow_mask = 0u64.wrapping_sub((borrow >> 63 & 1) as u64);
+        let mut carry = 0u64;
+        for i in 0..5 {
+            carry = difference[i] + (underflow_mask & L[i]) + (carry >> 52);
+            difference[i] = carry & MASK;
+        }
+        UnpackedScalar(difference)
+    }
+
+    /// If `self >= L`, subtract `L` once.
+    fn reduce_once(self) -> UnpackedScalar {
+        let l = L;
+        let mut borrow = 0i64;
+        let mut difference = [0u64; 5];
+        for i in 0..5 {
+            borrow = (self.0[i] as i64) - (l[i] as i64) - (borrow >> 52 & 1);
+            difference[i] = (borrow & (MASK as i64)) as u64;
+        }
+        let underflowed = ((borrow >> 63) & 1) == 1;
+        if underflowed {
+            self
+        } else {
+            UnpackedScalar(difference)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn scalar_from_u8(x: u8) -> Scalar {
+        let mut bytes = [0u8; 32];
+        bytes[0] = x;
+        Scalar::from_bytes_mod_order(bytes)
+    }
+
+    #[test]
+    fn from_bytes_mod_order_of_small_value_is_identity() {
+        let one = scalar_from_u8(1);
+        let mut expected = [0u8; 32];
+        expected[0] = 1;
+        assert_eq!(*one.as_bytes(), expected);
+    }
+
+    #[test]
+    fn mul_matches_repeated_add() {
+        let two = scalar_from_u8(2);
+        let three = scalar_from_u8(3);
+        let three_plus_three = &three + &three;
+        assert_eq!((&two * &three).as_bytes(), three_plus_three.as_bytes());
+    }
+
+    #[test]
+    fn add_then_sub_is_identity() {
+        let a = scalar_from_u8(17);
+        let b = scalar_from_u8(42);
+        let sum = &a + &b;
+        assert_eq!((&sum - &b).as_bytes(), a.as_bytes());
+    }
+
+    #[test]
+    fn sub_underflows_by_adding_back_l() {
+        let one = scalar_from_u8(1);
+        let two = scalar_from_u8(2);
+        let expected: [u8; 32] = [
+            236, 211, 245, 92, 26, 99, 18, 88, 214, 156, 247, 162, 222, 249, 222, 20, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 16,
+        ];
+        assert_eq!(*(&one - &two).as_bytes(), expected);
+    }
+}